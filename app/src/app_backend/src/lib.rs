@@ -2,6 +2,7 @@ use candid::{CandidType, Deserialize, Principal};
 use serde::Serialize;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 // Custom types for the eczema awareness system
@@ -14,6 +15,180 @@ pub struct EczemaResource {
     created_at: u64,
     updated_at: u64,
     verified: bool,
+    /// Defaults to the anonymous principal when decoding a legacy snapshot
+    /// whose records predate author tracking.
+    #[serde(default = "anonymous_principal")]
+    author: Principal,
+    /// Soft-delete marker: the timestamp at which the resource was deleted, or
+    /// `None` while it is live. Soft-deleted resources are hidden from all
+    /// list/search results until restored or purged.
+    #[serde(default)]
+    deleted_at: Option<u64>,
+    /// Extra typed metadata coerced from client-supplied strings at write time
+    /// (see [`Conversion`]), keyed by attribute name.
+    #[serde(default)]
+    attributes: HashMap<String, TypedValue>,
+}
+
+/// Fallback author for records deserialized from snapshots written before the
+/// field existed.
+fn anonymous_principal() -> Principal {
+    Principal::anonymous()
+}
+
+/// A value coerced from a client-supplied string into a concrete type.
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub enum TypedValue {
+    Bytes(Vec<u8>),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    /// Unix timestamp in seconds.
+    Timestamp(u64),
+}
+
+/// A string-to-type coercion, mirroring the way a storage layer declares the
+/// intended type of an otherwise untyped text field. Parse one from a string
+/// with [`FromStr`] and apply it with [`Conversion::convert`].
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub enum Conversion {
+    Bytes,
+    Integer,
+    Float,
+    Boolean,
+    /// Parse a base-10 Unix timestamp in seconds.
+    Timestamp,
+    /// Parse a date/time against a format string. Only `%Y-%m-%d` is currently
+    /// supported; the carried string records the intended format.
+    TimestampFmt(String),
+}
+
+impl FromStr for Conversion {
+    type Err = EczemaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some(fmt) = s.strip_prefix("timestamp:") {
+            return Ok(Conversion::TimestampFmt(fmt.to_string()));
+        }
+        match s.to_lowercase().as_str() {
+            "bytes" => Ok(Conversion::Bytes),
+            "integer" | "int" => Ok(Conversion::Integer),
+            "float" => Ok(Conversion::Float),
+            "boolean" | "bool" => Ok(Conversion::Boolean),
+            "timestamp" => Ok(Conversion::Timestamp),
+            other => Err(EczemaError::InvalidInput(format!(
+                "Unknown conversion '{other}'."
+            ))),
+        }
+    }
+}
+
+impl Conversion {
+    /// Coerces `input` into a [`TypedValue`], mapping any parse failure to a
+    /// descriptive [`EczemaError::InvalidInput`].
+    fn convert(&self, input: &str) -> Result<TypedValue, EczemaError> {
+        let trimmed = input.trim();
+        match self {
+            Conversion::Bytes => Ok(TypedValue::Bytes(input.as_bytes().to_vec())),
+            Conversion::Integer => trimmed
+                .parse::<i64>()
+                .map(TypedValue::Integer)
+                .map_err(|_| EczemaError::InvalidInput(format!("'{input}' is not an integer."))),
+            Conversion::Float => trimmed
+                .parse::<f64>()
+                .map(TypedValue::Float)
+                .map_err(|_| EczemaError::InvalidInput(format!("'{input}' is not a float."))),
+            Conversion::Boolean => match trimmed.to_lowercase().as_str() {
+                "true" => Ok(TypedValue::Boolean(true)),
+                "false" => Ok(TypedValue::Boolean(false)),
+                _ => Err(EczemaError::InvalidInput(format!(
+                    "'{input}' is not a boolean."
+                ))),
+            },
+            Conversion::Timestamp => trimmed
+                .parse::<u64>()
+                .map(TypedValue::Timestamp)
+                .map_err(|_| EczemaError::InvalidInput(format!("'{input}' is not a timestamp."))),
+            Conversion::TimestampFmt(fmt) => {
+                if fmt != "%Y-%m-%d" {
+                    return Err(EczemaError::InvalidInput(format!(
+                        "Unsupported timestamp format '{fmt}'."
+                    )));
+                }
+                parse_ymd(trimmed)
+                    .map(TypedValue::Timestamp)
+                    .ok_or_else(|| {
+                        EczemaError::InvalidInput(format!("'{input}' is not a %Y-%m-%d date."))
+                    })
+            }
+        }
+    }
+}
+
+/// Parses a `YYYY-MM-DD` calendar date into a Unix timestamp (seconds at
+/// midnight UTC) using the days-from-civil algorithm, avoiding any date crate.
+fn parse_ymd(s: &str) -> Option<u64> {
+    let parts: Vec<&str> = s.split('-').collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let year: i64 = parts[0].parse().ok()?;
+    let month: i64 = parts[1].parse().ok()?;
+    let day: i64 = parts[2].parse().ok()?;
+    if !(1..=12).contains(&month) {
+        return None;
+    }
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ => if leap { 29 } else { 28 },
+    };
+    if day < 1 || day > days_in_month {
+        return None;
+    }
+    // days_from_civil, shifting the year so March is month 0.
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146097 + doe - 719468;
+    if days < 0 {
+        return None;
+    }
+    Some(days as u64 * 86400)
+}
+
+/// A point-in-time snapshot of a resource's editable content, captured on every
+/// `update_resource` so moderators can review how an entry evolved.
+#[derive(CandidType, Serialize, Deserialize, Clone)]
+pub struct ResourceRevision {
+    timestamp: u64,
+    editor: Principal,
+    title: String,
+    description: String,
+    category: ResourceCategory,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    SuperAdmin,
+    Moderator,
+    Contributor,
+}
+
+impl Role {
+    /// Numeric rank used for "at least this role" comparisons; higher is more
+    /// privileged.
+    fn rank(self) -> u8 {
+        match self {
+            Role::SuperAdmin => 3,
+            Role::Moderator => 2,
+            Role::Contributor => 1,
+        }
+    }
 }
 
 #[derive(CandidType, Serialize, Deserialize, Clone, Copy, PartialEq)]
@@ -31,9 +206,55 @@ pub struct CreateResourcePayload {
     title: String,
     description: String,
     category: ResourceCategory,
+    /// Optional extra attributes supplied as `name -> (raw string, conversion)`
+    /// pairs, where the conversion is the textual name of a [`Conversion`]
+    /// (e.g. `"integer"`, `"boolean"`, `"timestamp:%Y-%m-%d"`). Both parts are
+    /// parsed and coerced to typed values at write time.
+    attributes: Option<HashMap<String, (String, String)>>,
+}
+
+/// Prefixes an `InvalidInput` message with the offending attribute name.
+fn prefix_attr(name: &str, error: EczemaError) -> EczemaError {
+    match error {
+        EczemaError::InvalidInput(msg) => {
+            EczemaError::InvalidInput(format!("attribute '{name}': {msg}"))
+        }
+        other => other,
+    }
+}
+
+/// Coerces a payload's optional raw attribute map into typed values, surfacing
+/// the attribute name alongside any parse or conversion failure.
+fn coerce_attributes(
+    attributes: Option<HashMap<String, (String, String)>>,
+) -> EczemaResult<HashMap<String, TypedValue>> {
+    let mut out = HashMap::new();
+    if let Some(attributes) = attributes {
+        for (name, (raw, conversion)) in attributes {
+            let conversion: Conversion =
+                conversion.parse().map_err(|e| prefix_attr(&name, e))?;
+            let value = conversion.convert(&raw).map_err(|e| prefix_attr(&name, e))?;
+            out.insert(name, value);
+        }
+    }
+    Ok(out)
 }
 
 #[derive(CandidType, Serialize, Deserialize)]
+pub struct ListParams {
+    limit: u32,
+    cursor: Option<u64>,
+    category: Option<ResourceCategory>,
+    verified_only: bool,
+}
+
+#[derive(CandidType, Serialize, Deserialize)]
+pub struct Page {
+    items: Vec<EczemaResource>,
+    next_cursor: Option<u64>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum EczemaError {
     NotFound,
     AlreadyExists,
@@ -46,7 +267,8 @@ type EczemaResult<T> = Result<T, EczemaError>;
 thread_local! {
     static ECZEMA_RESOURCES: RefCell<HashMap<u64, EczemaResource>> = RefCell::new(HashMap::new());
     static NEXT_ID: RefCell<u64> = RefCell::new(1);
-    static ADMIN: RefCell<Option<Principal>> = RefCell::new(None); // Define the admin
+    static ROLES: RefCell<HashMap<Principal, Role>> = RefCell::new(HashMap::new());
+    static REVISIONS: RefCell<HashMap<u64, Vec<ResourceRevision>>> = RefCell::new(HashMap::new());
 }
 
 fn get_timestamp() -> u64 {
@@ -66,21 +288,79 @@ fn validate_input(title: &str, description: &str) -> Result<(), EczemaError> {
     Ok(())
 }
 
-fn is_admin(caller: &Principal) -> bool {
-    ADMIN.with(|admin| admin.borrow().as_ref() == Some(caller))
+/// Returns the role assigned to `principal`, if any.
+fn role_of(roles: &HashMap<Principal, Role>, principal: &Principal) -> Option<Role> {
+    roles.get(principal).copied()
+}
+
+/// Whether `principal` holds at least `min` privilege.
+fn has_at_least(roles: &HashMap<Principal, Role>, principal: &Principal, min: Role) -> bool {
+    match role_of(roles, principal) {
+        Some(r) => r.rank() >= min.rank(),
+        None => false,
+    }
+}
+
+/// Bootstraps the first `SuperAdmin`. Succeeds only while no `SuperAdmin`
+/// exists; once one is set, roles are managed through `add_role`/`remove_role`.
+fn do_set_admin(roles: &mut HashMap<Principal, Role>, caller: Principal) -> EczemaResult<()> {
+    if roles.values().any(|r| *r == Role::SuperAdmin) {
+        return Err(EczemaError::Unauthorized);
+    }
+    roles.insert(caller, Role::SuperAdmin);
+    Ok(())
+}
+
+/// Grants `role` to `target`. Only a `SuperAdmin` may grant roles.
+fn do_add_role(
+    roles: &mut HashMap<Principal, Role>,
+    caller: &Principal,
+    target: Principal,
+    role: Role,
+) -> EczemaResult<()> {
+    if role_of(roles, caller) != Some(Role::SuperAdmin) {
+        return Err(EczemaError::Unauthorized);
+    }
+    roles.insert(target, role);
+    Ok(())
+}
+
+/// Revokes any role held by `target`. Only a `SuperAdmin` may revoke roles.
+fn do_remove_role(
+    roles: &mut HashMap<Principal, Role>,
+    caller: &Principal,
+    target: &Principal,
+) -> EczemaResult<()> {
+    if role_of(roles, caller) != Some(Role::SuperAdmin) {
+        return Err(EczemaError::Unauthorized);
+    }
+    roles.remove(target);
+    Ok(())
 }
 
 #[ic_cdk_macros::update]
-fn set_admin(caller: Principal) -> EczemaResult<()> {
-    ADMIN.with(|admin| {
-        *admin.borrow_mut() = Some(caller);
-        Ok(())
-    })
+fn set_admin() -> EczemaResult<()> {
+    let caller = ic_cdk::caller();
+    ROLES.with(|roles| do_set_admin(&mut roles.borrow_mut(), caller))
+}
+
+#[ic_cdk_macros::update]
+fn add_role(target: Principal, role: Role) -> EczemaResult<()> {
+    let caller = ic_cdk::caller();
+    ROLES.with(|roles| do_add_role(&mut roles.borrow_mut(), &caller, target, role))
+}
+
+#[ic_cdk_macros::update]
+fn remove_role(target: Principal) -> EczemaResult<()> {
+    let caller = ic_cdk::caller();
+    ROLES.with(|roles| do_remove_role(&mut roles.borrow_mut(), &caller, &target))
 }
 
 #[ic_cdk_macros::update]
 fn create_resource(payload: CreateResourcePayload) -> EczemaResult<EczemaResource> {
     validate_input(&payload.title, &payload.description)?;
+    let attributes = coerce_attributes(payload.attributes)?;
+    let caller = ic_cdk::caller();
     NEXT_ID.with(|next_id| {
         ECZEMA_RESOURCES.with(|resources| {
             let id = *next_id.borrow();
@@ -94,6 +374,9 @@ fn create_resource(payload: CreateResourcePayload) -> EczemaResult<EczemaResourc
                 created_at: timestamp,
                 updated_at: timestamp,
                 verified: false,
+                author: caller,
+                deleted_at: None,
+                attributes,
             };
 
             resources.borrow_mut().insert(id, resource.clone());
@@ -109,6 +392,7 @@ fn get_resource(id: u64) -> EczemaResult<EczemaResource> {
         resources
             .borrow()
             .get(&id)
+            .filter(|r| r.deleted_at.is_none())
             .cloned()
             .ok_or(EczemaError::NotFound)
     })
@@ -120,6 +404,7 @@ fn list_resources() -> Vec<EczemaResource> {
         resources
             .borrow()
             .values()
+            .filter(|r| r.deleted_at.is_none())
             .cloned()
             .collect()
     })
@@ -131,21 +416,92 @@ fn list_resources_by_category(category: ResourceCategory) -> Vec<EczemaResource>
         resources
             .borrow()
             .values()
-            .filter(|r| r.category == category)
+            .filter(|r| r.deleted_at.is_none() && r.category == category)
             .cloned()
             .collect()
     })
 }
 
+/// Maximum page size a single `list_resources_page` call may return.
+const MAX_PAGE_LIMIT: u32 = 100;
+
+#[ic_cdk_macros::query]
+fn list_resources_page(params: ListParams) -> EczemaResult<Page> {
+    if params.limit == 0 || params.limit > MAX_PAGE_LIMIT {
+        return Err(EczemaError::InvalidInput(format!(
+            "Limit must be between 1 and {MAX_PAGE_LIMIT}."
+        )));
+    }
+
+    ECZEMA_RESOURCES.with(|resources| {
+        let resources = resources.borrow();
+        // Collect the ids matching the filters in ascending order so paging is
+        // deterministic regardless of the underlying HashMap iteration order.
+        let mut ids: Vec<u64> = resources
+            .values()
+            .filter(|r| r.deleted_at.is_none())
+            .filter(|r| match params.category {
+                Some(c) => r.category == c,
+                None => true,
+            })
+            .filter(|r| !params.verified_only || r.verified)
+            .map(|r| r.id)
+            .collect();
+        ids.sort_unstable();
+
+        let start = match params.cursor {
+            Some(cursor) => ids.partition_point(|&id| id <= cursor),
+            None => 0,
+        };
+
+        let items: Vec<EczemaResource> = ids[start..]
+            .iter()
+            .take(params.limit as usize)
+            .map(|id| resources[id].clone())
+            .collect();
+
+        let next_cursor = if start + items.len() < ids.len() {
+            items.last().map(|r| r.id)
+        } else {
+            None
+        };
+
+        Ok(Page { items, next_cursor })
+    })
+}
+
 #[ic_cdk_macros::update]
 fn update_resource(id: u64, payload: CreateResourcePayload) -> EczemaResult<EczemaResource> {
     validate_input(&payload.title, &payload.description)?;
+    let attributes = coerce_attributes(payload.attributes)?;
+    let caller = ic_cdk::caller();
     ECZEMA_RESOURCES.with(|resources| {
         let mut resources = resources.borrow_mut();
         if let Some(resource) = resources.get_mut(&id) {
+            // Contributors may edit their own resources; editing someone else's
+            // requires Moderator privileges.
+            if resource.author != caller
+                && !ROLES.with(|r| has_at_least(&r.borrow(), &caller, Role::Moderator))
+            {
+                return Err(EczemaError::Unauthorized);
+            }
+            if resource.deleted_at.is_some() {
+                return Err(EczemaError::NotFound);
+            }
+            // Snapshot the content being replaced before overwriting it.
+            let revision = ResourceRevision {
+                timestamp: get_timestamp(),
+                editor: caller,
+                title: resource.title.clone(),
+                description: resource.description.clone(),
+                category: resource.category,
+            };
+            REVISIONS.with(|revs| revs.borrow_mut().entry(id).or_default().push(revision));
+
             resource.title = payload.title;
             resource.description = payload.description;
             resource.category = payload.category;
+            resource.attributes = attributes;
             resource.updated_at = get_timestamp();
             Ok(resource.clone())
         } else {
@@ -156,19 +512,92 @@ fn update_resource(id: u64, payload: CreateResourcePayload) -> EczemaResult<Ecze
 
 #[ic_cdk_macros::update]
 fn delete_resource(id: u64) -> EczemaResult<()> {
+    let caller = ic_cdk::caller();
     ECZEMA_RESOURCES.with(|resources| {
-        if resources.borrow_mut().remove(&id).is_some() {
-            Ok(())
-        } else {
-            Err(EczemaError::NotFound)
+        let mut resources = resources.borrow_mut();
+        let resource = resources.get_mut(&id).ok_or(EczemaError::NotFound)?;
+        // Contributors may delete their own resources; deleting someone else's
+        // requires Moderator privileges.
+        if resource.author != caller
+            && !ROLES.with(|r| has_at_least(&r.borrow(), &caller, Role::Moderator))
+        {
+            return Err(EczemaError::Unauthorized);
         }
+        if resource.deleted_at.is_some() {
+            return Err(EczemaError::NotFound);
+        }
+        // Soft delete: keep the record so it can be restored or audited.
+        resource.deleted_at = Some(get_timestamp());
+        Ok(())
     })
 }
 
+#[ic_cdk_macros::update]
+fn restore_resource(id: u64) -> EczemaResult<EczemaResource> {
+    let caller = ic_cdk::caller();
+    ECZEMA_RESOURCES.with(|resources| {
+        let mut resources = resources.borrow_mut();
+        let resource = resources.get_mut(&id).ok_or(EczemaError::NotFound)?;
+        // Same authorization as deletion: owners and Moderators may restore.
+        if resource.author != caller
+            && !ROLES.with(|r| has_at_least(&r.borrow(), &caller, Role::Moderator))
+        {
+            return Err(EczemaError::Unauthorized);
+        }
+        if resource.deleted_at.is_none() {
+            return Err(EczemaError::NotFound);
+        }
+        resource.deleted_at = None;
+        resource.updated_at = get_timestamp();
+        Ok(resource.clone())
+    })
+}
+
+#[ic_cdk_macros::query]
+fn get_resource_history(id: u64) -> EczemaResult<Vec<ResourceRevision>> {
+    ECZEMA_RESOURCES.with(|resources| {
+        if !resources.borrow().contains_key(&id) {
+            return Err(EczemaError::NotFound);
+        }
+        Ok(REVISIONS.with(|revs| revs.borrow().get(&id).cloned().unwrap_or_default()))
+    })
+}
+
+#[ic_cdk_macros::update]
+fn purge_deleted(older_than_secs: u64) -> EczemaResult<u64> {
+    let caller = ic_cdk::caller();
+    if !ROLES.with(|r| has_at_least(&r.borrow(), &caller, Role::SuperAdmin)) {
+        return Err(EczemaError::Unauthorized);
+    }
+    let cutoff = get_timestamp().saturating_sub(older_than_secs);
+    let purged: Vec<u64> = ECZEMA_RESOURCES.with(|resources| {
+        let mut resources = resources.borrow_mut();
+        let ids: Vec<u64> = resources
+            .iter()
+            .filter(|(_, r)| match r.deleted_at {
+                Some(t) => t <= cutoff,
+                None => false,
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &ids {
+            resources.remove(id);
+        }
+        ids
+    });
+    REVISIONS.with(|revs| {
+        let mut revs = revs.borrow_mut();
+        for id in &purged {
+            revs.remove(id);
+        }
+    });
+    Ok(purged.len() as u64)
+}
+
 #[ic_cdk_macros::update]
 fn verify_resource(id: u64) -> EczemaResult<EczemaResource> {
     let caller = ic_cdk::caller();
-    if !is_admin(&caller) {
+    if !ROLES.with(|r| has_at_least(&r.borrow(), &caller, Role::Moderator)) {
         return Err(EczemaError::Unauthorized);
     }
 
@@ -191,6 +620,7 @@ fn search_resources(query: String) -> Vec<EczemaResource> {
         resources
             .borrow()
             .values()
+            .filter(|r| r.deleted_at.is_none())
             .filter(|r| {
                 r.title.to_lowercase().contains(&query) ||
                 r.description.to_lowercase().contains(&query)
@@ -200,23 +630,351 @@ fn search_resources(query: String) -> Vec<EczemaResource> {
     })
 }
 
+// Ranked full-text search.
+//
+// Tokenizes the query and each resource's title/description into lowercased
+// words and matches each query word against resource words allowing an edit
+// distance that grows with the word length: exact for words up to 4 chars,
+// Levenshtein <=1 for 5-8 chars, <=2 for longer words. This keeps short words
+// strict (where a single edit changes the word entirely) while tolerating
+// typos in longer terms, roughly the way MeiliSearch scales its typo budget.
+
+/// Splits a string into lowercased alphanumeric word tokens.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Maximum number of typos tolerated for a word of the given length.
+fn typo_budget(len: usize) -> u32 {
+    match len {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    }
+}
+
+/// Levenshtein edit distance between two words, bounded by `max`: once the
+/// minimum achievable distance on a row exceeds `max` the computation stops
+/// early and returns `max + 1`.
+fn levenshtein_within(a: &str, b: &str, max: u32) -> u32 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) as u32 > max {
+        return max + 1;
+    }
+    let mut prev: Vec<u32> = (0..=b.len() as u32).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut cur = vec![i as u32 + 1];
+        let mut row_min = cur[0];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            let val = (prev[j] + cost)
+                .min(prev[j + 1] + 1)
+                .min(cur[j] + 1);
+            cur.push(val);
+            row_min = row_min.min(val);
+        }
+        if row_min > max {
+            return max + 1;
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// Match a single query word against a document word, returning the typo count
+/// if it is within the word's typo budget.
+fn match_word(query_word: &str, doc_word: &str) -> Option<u32> {
+    let budget = typo_budget(query_word.chars().count());
+    if budget == 0 {
+        return if query_word == doc_word { Some(0) } else { None };
+    }
+    let dist = levenshtein_within(query_word, doc_word, budget);
+    if dist <= budget {
+        Some(dist)
+    } else {
+        None
+    }
+}
+
+/// The ranking key for a resource against a query, compared lexicographically.
+/// Sorting resources by this key descending yields best-first ordering:
+///   0: number of distinct query words matched (more is better)
+///   1: negated total typos (fewer typos is better)
+///   2: negated proximity span (a tighter span is better)
+///   3: attribute weight (a title match outranks a description match)
+type RankKey = (usize, i64, i64, u8);
+
+/// Scores a resource against the tokenized query, returning a ranking key and a
+/// normalized relevance score in `[0, 1]`, or `None` if nothing matched.
+fn score_resource(resource: &EczemaResource, query_words: &[String]) -> Option<(RankKey, f64)> {
+    if query_words.is_empty() {
+        return None;
+    }
+
+    let title_words = tokenize(&resource.title);
+    let desc_words = tokenize(&resource.description);
+
+    let mut matched = 0usize;
+    let mut total_typos = 0u32;
+    let mut title_match = false;
+    // Position of the best match for each matched query word in a combined
+    // coordinate space (title words first, then description words), used to
+    // compute the proximity span across both attributes.
+    let mut matched_positions: Vec<usize> = Vec::new();
+
+    for qw in query_words {
+        // Candidate: (typos, in_title, combined_position).
+        let mut best: Option<(u32, bool, usize)> = None;
+        let candidates = title_words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| (w, true, i))
+            .chain(
+                desc_words
+                    .iter()
+                    .enumerate()
+                    .map(|(j, w)| (w, false, title_words.len() + j)),
+            );
+        for (word, in_title, pos) in candidates {
+            if let Some(typos) = match_word(qw, word) {
+                let cand = (typos, in_title, pos);
+                // Prefer a title match; among the same attribute prefer fewer
+                // typos.
+                let better = match &best {
+                    Some(b) => (cand.1, std::cmp::Reverse(cand.0)) > (b.1, std::cmp::Reverse(b.0)),
+                    None => true,
+                };
+                if better {
+                    best = Some(cand);
+                }
+            }
+        }
+        if let Some((typos, in_title, pos)) = best {
+            matched += 1;
+            total_typos += typos;
+            title_match |= in_title;
+            matched_positions.push(pos);
+        }
+    }
+
+    if matched == 0 {
+        return None;
+    }
+
+    let span = if matched_positions.len() >= 2 {
+        let min = *matched_positions.iter().min().unwrap();
+        let max = *matched_positions.iter().max().unwrap();
+        (max - min) as i64
+    } else {
+        0
+    };
+    let attribute_weight = if title_match { 1 } else { 0 };
+
+    let key: RankKey = (matched, -(total_typos as i64), -span, attribute_weight);
+
+    // Relevance score in [0, 1]: fraction of query words matched, discounted by
+    // the average typo cost per matched word and nudged up for title matches.
+    let coverage = matched as f64 / query_words.len() as f64;
+    let typo_penalty = 1.0 - (total_typos as f64 / (matched as f64 * 2.0 + 1.0));
+    let attr_bonus = if title_match { 1.0 } else { 0.85 };
+    let score = (coverage * typo_penalty * attr_bonus).clamp(0.0, 1.0);
+
+    Some((key, score))
+}
+
+#[ic_cdk_macros::query]
+fn search_resources_ranked(query: String) -> Vec<(EczemaResource, f64)> {
+    let query_words = tokenize(&query);
+    ECZEMA_RESOURCES.with(|resources| {
+        let mut scored: Vec<(EczemaResource, RankKey, f64)> = resources
+            .borrow()
+            .values()
+            .filter(|r| r.deleted_at.is_none())
+            .filter_map(|r| score_resource(r, &query_words).map(|(k, s)| (r.clone(), k, s)))
+            .collect();
+        scored.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.id.cmp(&b.0.id)));
+        scored.into_iter().map(|(r, _, s)| (r, s)).collect()
+    })
+}
+
+/// Schema version of the serialized state written by the current release. Bump
+/// this whenever `StableState`/`EczemaResource` gains or changes a field and add
+/// a matching `migrate_vN_to_vN+1` arm to `run_migrations`.
+const STABLE_VERSION: u32 = 3;
+
+/// Versioned envelope persisted across upgrades in a single `stable_save`.
+///
+/// Two layers keep upgrades non-breaking. First, every field added after the
+/// initial release is `#[serde(default)]` (here and on `EczemaResource`), so
+/// Candid can decode an older snapshot that lacks those fields — the decode is
+/// what would otherwise fail, and it happens before any migration runs.
+/// Second, `post_upgrade` reads `version` and runs the ordered migrations below
+/// for value-level transforms that a plain field default cannot express (e.g.
+/// recomputing a field from others, or backfilling a non-defaultable value).
+#[derive(CandidType, Serialize, Deserialize)]
+struct StableState {
+    version: u32,
+    resources: HashMap<u64, EczemaResource>,
+    next_id: u64,
+    roles: HashMap<Principal, Role>,
+    #[serde(default)]
+    revisions: HashMap<u64, Vec<ResourceRevision>>,
+}
+
+/// Applies migrations in order until the snapshot matches `STABLE_VERSION`.
+/// Each step upgrades the already-decoded state by exactly one version; fields
+/// that merely need a default are handled at decode time by `#[serde(default)]`
+/// (see [`StableState`]), so a step only does work a default cannot.
+fn run_migrations(mut state: StableState) -> StableState {
+    while state.version < STABLE_VERSION {
+        state = match state.version {
+            1 => migrate_v1_to_v2(state),
+            2 => migrate_v2_to_v3(state),
+            _ => {
+                // Unknown/older version with no registered migration: adopt the
+                // current version verbatim rather than refusing the upgrade.
+                state.version = STABLE_VERSION;
+                state
+            }
+        };
+    }
+    state
+}
+
+/// v2 added the edit-history subsystem (`revisions`) and the `deleted_at`
+/// soft-delete marker, both `#[serde(default)]` and filled at decode time, so
+/// this step has no value-level transform to perform and only stamps the
+/// version. It stays as an explicit step so future v2-specific backfills have a
+/// home.
+fn migrate_v1_to_v2(mut state: StableState) -> StableState {
+    state.version = 2;
+    state
+}
+
+/// v3 added the typed `attributes` map to `EczemaResource`, decoded as an empty
+/// map on existing records, so this step likewise only stamps the version.
+fn migrate_v2_to_v3(mut state: StableState) -> StableState {
+    state.version = 3;
+    state
+}
+
 #[ic_cdk_macros::init]
 fn init() {}
 
 #[ic_cdk_macros::pre_upgrade]
 fn pre_upgrade() {
-    ECZEMA_RESOURCES.with(|resources| ic_cdk::storage::stable_save((resources.borrow().clone(),)).unwrap());
-    NEXT_ID.with(|next_id| ic_cdk::storage::stable_save((next_id.borrow().clone(),)).unwrap());
+    let state = ECZEMA_RESOURCES.with(|resources| {
+        NEXT_ID.with(|next_id| {
+            ROLES.with(|roles| {
+                REVISIONS.with(|revisions| StableState {
+                    version: STABLE_VERSION,
+                    resources: resources.borrow().clone(),
+                    next_id: *next_id.borrow(),
+                    roles: roles.borrow().clone(),
+                    revisions: revisions.borrow().clone(),
+                })
+            })
+        })
+    });
+    ic_cdk::storage::stable_save((state,)).unwrap();
 }
 
 #[ic_cdk_macros::post_upgrade]
 fn post_upgrade() {
-    let (stored_resources,): (HashMap<u64, EczemaResource>,) = ic_cdk::storage::stable_restore().unwrap();
-    let (stored_next_id,): (u64,) = ic_cdk::storage::stable_restore().unwrap();
+    let (state,): (StableState,) = ic_cdk::storage::stable_restore().unwrap();
+    let state = run_migrations(state);
 
-    ECZEMA_RESOURCES.with(|resources| *resources.borrow_mut() = stored_resources);
-    NEXT_ID.with(|next_id| *next_id.borrow_mut() = stored_next_id);
+    ECZEMA_RESOURCES.with(|resources| *resources.borrow_mut() = state.resources);
+    NEXT_ID.with(|next_id| *next_id.borrow_mut() = state.next_id);
+    ROLES.with(|roles| *roles.borrow_mut() = state.roles);
+    REVISIONS.with(|revisions| *revisions.borrow_mut() = state.revisions);
 }
 
 // Export the Candid interface
 ic_cdk::export_candid!();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn principal(byte: u8) -> Principal {
+        Principal::from_slice(&[byte])
+    }
+
+    #[test]
+    fn set_admin_bootstraps_first_super_admin() {
+        let mut roles = HashMap::new();
+        let alice = principal(1);
+        assert_eq!(do_set_admin(&mut roles, alice), Ok(()));
+        assert_eq!(role_of(&roles, &alice), Some(Role::SuperAdmin));
+    }
+
+    #[test]
+    fn set_admin_rejects_once_super_admin_exists() {
+        let mut roles = HashMap::new();
+        do_set_admin(&mut roles, principal(1)).unwrap();
+        assert_eq!(
+            do_set_admin(&mut roles, principal(2)),
+            Err(EczemaError::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn only_super_admin_may_add_roles() {
+        let mut roles = HashMap::new();
+        let admin = principal(1);
+        let mod_p = principal(2);
+        let other = principal(3);
+        do_set_admin(&mut roles, admin).unwrap();
+
+        // A non-super-admin cannot grant roles.
+        assert_eq!(
+            do_add_role(&mut roles, &other, mod_p, Role::Moderator),
+            Err(EczemaError::Unauthorized)
+        );
+        // The super admin can.
+        assert_eq!(do_add_role(&mut roles, &admin, mod_p, Role::Moderator), Ok(()));
+        assert_eq!(role_of(&roles, &mod_p), Some(Role::Moderator));
+        // A freshly minted moderator still cannot grant roles.
+        assert_eq!(
+            do_add_role(&mut roles, &mod_p, other, Role::Contributor),
+            Err(EczemaError::Unauthorized)
+        );
+    }
+
+    #[test]
+    fn only_super_admin_may_remove_roles() {
+        let mut roles = HashMap::new();
+        let admin = principal(1);
+        let mod_p = principal(2);
+        do_set_admin(&mut roles, admin).unwrap();
+        do_add_role(&mut roles, &admin, mod_p, Role::Moderator).unwrap();
+
+        assert_eq!(
+            do_remove_role(&mut roles, &mod_p, &admin),
+            Err(EczemaError::Unauthorized)
+        );
+        assert_eq!(do_remove_role(&mut roles, &admin, &mod_p), Ok(()));
+        assert_eq!(role_of(&roles, &mod_p), None);
+    }
+
+    #[test]
+    fn has_at_least_respects_role_ranking() {
+        let mut roles = HashMap::new();
+        let admin = principal(1);
+        let mod_p = principal(2);
+        let contrib = principal(3);
+        roles.insert(admin, Role::SuperAdmin);
+        roles.insert(mod_p, Role::Moderator);
+        roles.insert(contrib, Role::Contributor);
+
+        assert!(has_at_least(&roles, &admin, Role::Moderator));
+        assert!(has_at_least(&roles, &mod_p, Role::Moderator));
+        assert!(!has_at_least(&roles, &contrib, Role::Moderator));
+        assert!(!has_at_least(&roles, &principal(9), Role::Contributor));
+    }
+}